@@ -0,0 +1,204 @@
+use std::ops::Range;
+
+/// A single lexical token, carrying the byte span it was read from in the
+/// original source so parse errors can point at the offending text.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Token {
+  pub(crate) kind: TokenKind,
+  pub(crate) span: Range<usize>,
+}
+
+/// The kind of a [Token].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TokenKind {
+  Num(f32),
+  Op(char),
+  LParen,
+  RParen,
+  Equals,
+  Ident(String),
+}
+
+/// A lexing error, carrying the byte span of the offending character.
+#[derive(Debug)]
+pub(crate) struct LexError {
+  pub(crate) span: Range<usize>,
+}
+
+/// Tokenize `input` into a flat list of [Token], skipping whitespace.
+///
+/// A `-` is folded into the following number literal (producing a negative
+/// [TokenKind::Num]) wherever an operand is expected -- at the start of
+/// input, or right after another operator, `(` or `=` -- mirroring how the
+/// old combinator parser only consumed a leading `-` while parsing a fresh
+/// operand. Everywhere else `-` is emitted as a binary [TokenKind::Op].
+pub(crate) fn tokenize(input: &str) -> Result<Vec<Token>, LexError> {
+  let mut tokens: Vec<Token> = Vec::new();
+  let mut i = 0;
+  while let Some(c) = input[i..].chars().next() {
+    if c.is_whitespace() {
+      i += c.len_utf8();
+      continue;
+    }
+    let expects_operand = matches!(
+      tokens.last().map(|t| &t.kind),
+      None | Some(TokenKind::Op(_)) | Some(TokenKind::LParen) | Some(TokenKind::Equals)
+    );
+    if (c == '-' && expects_operand) || c.is_ascii_digit() {
+      let len = lex_number_len(&input[i..]).ok_or(LexError { span: i..i + 1 })?;
+      let value = input[i..i + len]
+        .parse::<f32>()
+        .map_err(|_| LexError { span: i..i + len })?;
+      tokens.push(Token {
+        kind: TokenKind::Num(value),
+        span: i..i + len,
+      });
+      i += len;
+    } else if c.is_alphabetic() {
+      let len = input[i..]
+        .find(|ch: char| !ch.is_alphanumeric())
+        .unwrap_or(input.len() - i);
+      tokens.push(Token {
+        kind: TokenKind::Ident(input[i..i + len].to_string()),
+        span: i..i + len,
+      });
+      i += len;
+    } else if c == '(' {
+      tokens.push(Token {
+        kind: TokenKind::LParen,
+        span: i..i + 1,
+      });
+      i += 1;
+    } else if c == ')' {
+      tokens.push(Token {
+        kind: TokenKind::RParen,
+        span: i..i + 1,
+      });
+      i += 1;
+    } else if c == '=' {
+      tokens.push(Token {
+        kind: TokenKind::Equals,
+        span: i..i + 1,
+      });
+      i += 1;
+    } else if "+-*/^".contains(c) {
+      tokens.push(Token {
+        kind: TokenKind::Op(c),
+        span: i..i + 1,
+      });
+      i += 1;
+    } else {
+      return Err(LexError {
+        span: i..i + c.len_utf8(),
+      });
+    }
+  }
+  Ok(tokens)
+}
+
+/// Return the byte length of the (optionally signed) number literal at the
+/// start of `input`, or `None` if it doesn't start with one.
+fn lex_number_len(input: &str) -> Option<usize> {
+  let bytes = input.as_bytes();
+  let mut i = usize::from(bytes.first() == Some(&b'-'));
+  let digit_start = i;
+  while bytes.get(i).is_some_and(u8::is_ascii_digit) {
+    i += 1;
+  }
+  if i == digit_start {
+    return None;
+  }
+  if bytes.get(i) == Some(&b'.') && bytes.get(i + 1).is_some_and(u8::is_ascii_digit) {
+    i += 1;
+    while bytes.get(i).is_some_and(u8::is_ascii_digit) {
+      i += 1;
+    }
+  }
+  Some(i)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn kinds(input: &str) -> Vec<TokenKind> {
+    tokenize(input)
+      .unwrap()
+      .into_iter()
+      .map(|t| t.kind)
+      .collect()
+  }
+
+  #[test]
+  fn test_numbers() {
+    assert_eq!(kinds("10.5"), vec![TokenKind::Num(10.5)]);
+    assert_eq!(kinds("-10"), vec![TokenKind::Num(-10.)]);
+    assert_eq!(
+      kinds("3.141592653589793"),
+      vec![TokenKind::Num(std::f32::consts::PI)]
+    );
+  }
+
+  #[test]
+  fn test_minus_is_operator_after_operand() {
+    assert_eq!(
+      kinds("10-10"),
+      vec![
+        TokenKind::Num(10.),
+        TokenKind::Op('-'),
+        TokenKind::Num(10.),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_minus_folds_into_sign_at_operand_position() {
+    assert_eq!(
+      kinds("5 + -2"),
+      vec![
+        TokenKind::Num(5.),
+        TokenKind::Op('+'),
+        TokenKind::Num(-2.),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_function_and_parens() {
+    assert_eq!(
+      kinds("sin(0)"),
+      vec![
+        TokenKind::Ident("sin".to_string()),
+        TokenKind::LParen,
+        TokenKind::Num(0.),
+        TokenKind::RParen,
+      ]
+    );
+  }
+
+  #[test]
+  fn test_equation() {
+    assert_eq!(
+      kinds("1 + 1 = 2"),
+      vec![
+        TokenKind::Num(1.),
+        TokenKind::Op('+'),
+        TokenKind::Num(1.),
+        TokenKind::Equals,
+        TokenKind::Num(2.),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_invalid_character() {
+    let err = tokenize("1 & 1").unwrap_err();
+    assert_eq!(err.span, 2..3);
+  }
+
+  #[test]
+  fn test_invalid_multibyte_character() {
+    let err = tokenize("1 + \u{1f600}").unwrap_err();
+    assert_eq!(err.span, 4..8);
+  }
+}
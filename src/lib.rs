@@ -1,17 +1,25 @@
 use parser::parse_equation;
-use std::str::FromStr;
+use std::{collections::HashMap, str::FromStr};
 
+mod lexer;
 mod parser;
 
 /// A recursive Expression.
 #[derive(Debug)]
 enum Expr {
   Num(f32),
+  Var(String),
   Add(Box<Expr>, Box<Expr>),
   Subtract(Box<Expr>, Box<Expr>),
   Multiply(Box<Expr>, Box<Expr>),
   Divide(Box<Expr>, Box<Expr>),
   Exponent(Box<Expr>, Box<Expr>),
+  Sin(Box<Expr>),
+  Cos(Box<Expr>),
+  Exp(Box<Expr>),
+  Ln(Box<Expr>),
+  Sqrt(Box<Expr>),
+  Abs(Box<Expr>),
 }
 
 impl std::fmt::Display for Expr {
@@ -19,15 +27,35 @@ impl std::fmt::Display for Expr {
     use crate::Expr::*;
     match self {
       Num(num) => write!(f, "{num}"),
+      Var(name) => write!(f, "{name}"),
       Add(expr1, expr2) => write!(f, "{expr1} + {expr2}"),
       Subtract(expr1, expr2) => write!(f, "{expr1} - {expr2}"),
       Multiply(expr1, expr2) => write!(f, "{expr1} * {expr2}"),
       Divide(expr1, expr2) => write!(f, "{expr1} / {expr2}"),
       Exponent(expr1, expr2) => write!(f, "{expr1}^{expr2}"),
+      Sin(expr) => write!(f, "sin({expr})"),
+      Cos(expr) => write!(f, "cos({expr})"),
+      Exp(expr) => write!(f, "exp({expr})"),
+      Ln(expr) => write!(f, "ln({expr})"),
+      Sqrt(expr) => write!(f, "sqrt({expr})"),
+      Abs(expr) => write!(f, "abs({expr})"),
     }
   }
 }
 
+/// Selectable operator-precedence profile for parsing an [Equation].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precedence {
+  /// The usual arithmetic precedence: `^` binds tighter than `*`/`/`, which
+  /// bind tighter than `+`/`-`.
+  Standard,
+  /// All binary operators share one precedence level and are evaluated left
+  /// to right, e.g. `1 + 2 * 3 == 9`.
+  FlatLeftToRight,
+  /// `+`/`-` bind tighter than `*`/`/`, the reverse of [Precedence::Standard].
+  AddBeforeMultiply,
+}
+
 /// An equation with LHS equation and RHS result.
 #[derive(Debug)]
 pub struct Equation {
@@ -47,28 +75,87 @@ impl std::fmt::Display for Equation {
   }
 }
 
-/// Evaluate the expression for this equation.
-fn evaluate(expr: &Expr) -> f32 {
+/// An error produced while evaluating an [Expr] tree.
+#[derive(Debug, PartialEq)]
+pub enum EvalError {
+  /// A `Divide` node's divisor evaluated to zero.
+  DivisionByZero,
+  /// A function was applied outside of its mathematical domain, e.g. `ln(-1)`.
+  DomainError,
+  /// The result of an operation is not a finite `f32`.
+  Overflow,
+  /// An [Expr::Var] referenced a name not present in the evaluation environment.
+  UnboundVariable(String),
+}
+
+/// Evaluate the expression for this equation, looking up any [Expr::Var]
+/// leaves in `env`.
+fn evaluate(expr: &Expr, env: &HashMap<String, f32>) -> Result<f32, EvalError> {
   use crate::Expr::*;
-  match expr {
+  let result = match expr {
     Num(num) => *num,
-    Add(expr1, expr2) => evaluate(expr1) + evaluate(expr2),
-    Subtract(expr1, expr2) => evaluate(expr1) - evaluate(expr2),
-    Multiply(expr1, expr2) => evaluate(expr1) * evaluate(expr2),
-    Divide(expr1, expr2) => evaluate(expr1) / evaluate(expr2),
-    Exponent(expr1, expr2) => evaluate(expr1).powf(evaluate(expr2)),
+    Var(name) => env
+      .get(name)
+      .cloned()
+      .ok_or_else(|| EvalError::UnboundVariable(name.clone()))?,
+    Add(expr1, expr2) => evaluate(expr1, env)? + evaluate(expr2, env)?,
+    Subtract(expr1, expr2) => evaluate(expr1, env)? - evaluate(expr2, env)?,
+    Multiply(expr1, expr2) => evaluate(expr1, env)? * evaluate(expr2, env)?,
+    Divide(expr1, expr2) => {
+      let divisor = evaluate(expr2, env)?;
+      if divisor == 0.0 {
+        return Err(EvalError::DivisionByZero);
+      }
+      evaluate(expr1, env)? / divisor
+    }
+    Exponent(expr1, expr2) => evaluate(expr1, env)?.powf(evaluate(expr2, env)?),
+    Sin(expr) => evaluate(expr, env)?.sin(),
+    Cos(expr) => evaluate(expr, env)?.cos(),
+    Exp(expr) => evaluate(expr, env)?.exp(),
+    Ln(expr) => {
+      let value = evaluate(expr, env)?;
+      if value <= 0.0 {
+        return Err(EvalError::DomainError);
+      }
+      value.ln()
+    }
+    Sqrt(expr) => {
+      let value = evaluate(expr, env)?;
+      if value < 0.0 {
+        return Err(EvalError::DomainError);
+      }
+      value.sqrt()
+    }
+    Abs(expr) => evaluate(expr, env)?.abs(),
+  };
+  if result.is_finite() {
+    Ok(result)
+  } else {
+    Err(EvalError::Overflow)
   }
 }
 
 impl Equation {
   /// Evaluate the expression for this equation.
-  pub fn eval(&self) -> f32 {
-    evaluate(&self.expression)
+  pub fn eval(&self) -> Result<f32, EvalError> {
+    self.eval_with(&HashMap::new())
+  }
+
+  /// Evaluate the expression for this equation, binding any variables from `env`.
+  pub fn eval_with(&self, env: &HashMap<String, f32>) -> Result<f32, EvalError> {
+    evaluate(&self.expression, env)
   }
 
   /// Evaluate the expression for this equation and check if it matches RHS expectation.
   pub fn is_correct(&self) -> bool {
-    self.eval() == self.expected
+    matches!(self.eval(), Ok(value) if value == self.expected)
+  }
+
+  /// Evaluate the expression for this equation and check if it matches the RHS
+  /// expectation within `eps`, tolerating the rounding error that division and
+  /// exponentiation accumulate (e.g. `sqrt(2) * sqrt(2) = 2`).
+  pub fn is_correct_within(&self, eps: f32) -> bool {
+    matches!(self.eval(), Ok(value) if (value - self.expected).abs() <= eps)
   }
 }
 
@@ -76,13 +163,23 @@ impl Equation {
 #[derive(Debug)]
 pub struct InvalidEquation(String);
 
+impl std::fmt::Display for InvalidEquation {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "invalid equation: {}", self.0)
+  }
+}
+
 impl FromStr for Equation {
   type Err = InvalidEquation;
   fn from_str(s: &str) -> Result<Self, Self::Err> {
-    match parse_equation(s) {
-      Ok((_, p)) => Ok(p),
-      Err(e) => Err(InvalidEquation(e.to_string())),
-    }
+    Equation::from_str_with(s, Precedence::Standard)
+  }
+}
+
+impl Equation {
+  /// Parse an [Equation] from a string slice using the given [Precedence] profile.
+  pub fn from_str_with(s: &str, precedence: Precedence) -> Result<Self, InvalidEquation> {
+    parse_equation(s, precedence).map_err(InvalidEquation)
   }
 }
 
@@ -115,5 +212,46 @@ mod test {
     test_parser("1.5 + 2.5 = 4.0", true);
     test_parser("   1.5 +  2.5 = 4.0", true);
     test_parser("   1.5 +  2.5 * 5 = 14  ", true);
+    test_parser("sin(0) + 1 = 1", true);
+    test_parser("sqrt(16) = 4", true);
+    test_parser("cos(0) * 5 = 5", true);
+    test_parser("2^3^2 = 512", true);
+  }
+
+  #[test]
+  fn test_eval_with_variables() {
+    let equation = "x + 2 = 5".parse::<Equation>().unwrap();
+    let env = HashMap::from([("x".to_string(), 3.0)]);
+    assert_eq!(equation.eval_with(&env), Ok(5.0));
+  }
+
+  #[test]
+  fn test_unbound_variable() {
+    let equation = "x + 2 = 5".parse::<Equation>().unwrap();
+    assert_eq!(
+      equation.eval(),
+      Err(EvalError::UnboundVariable("x".to_string()))
+    );
+    assert!(!equation.is_correct());
+  }
+
+  #[test]
+  fn test_division_by_zero() {
+    let equation = "1 / 0 = 5".parse::<Equation>().unwrap();
+    assert_eq!(equation.eval(), Err(EvalError::DivisionByZero));
+    assert!(!equation.is_correct());
+  }
+
+  #[test]
+  fn test_domain_error() {
+    let equation = "sqrt(-1) = 0".parse::<Equation>().unwrap();
+    assert_eq!(equation.eval(), Err(EvalError::DomainError));
+  }
+
+  #[test]
+  fn test_is_correct_within_tolerance() {
+    let equation = "sqrt(2) * sqrt(2) = 2".parse::<Equation>().unwrap();
+    assert!(!equation.is_correct());
+    assert!(equation.is_correct_within(0.0001));
   }
 }
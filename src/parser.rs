@@ -1,16 +1,47 @@
-use crate::{Equation, Expr};
+use crate::{
+  lexer::{tokenize, Token, TokenKind},
+  Equation, Expr, Precedence,
+};
 use nom::{
   branch::alt,
-  character::complete::{char, digit1, space0},
-  combinator::{map, map_res, opt, recognize},
-  multi::many0,
-  sequence::{delimited, preceded, terminated, tuple},
+  combinator::map,
+  sequence::{delimited, preceded, tuple},
   IResult,
 };
 
-/// Parse an [Equation] from a string slice.
-pub fn parse_equation(input: &str) -> IResult<&str, Equation> {
-  let mut parser = tuple((parse_expression, rhs));
+/// Parse an [Equation] from a string slice using the given [Precedence] profile.
+pub fn parse_equation(input: &str, precedence: Precedence) -> Result<Equation, String> {
+  let tokens = tokenize(input).map_err(|e| {
+    format!(
+      "unrecognized character at byte {}..{}",
+      e.span.start, e.span.end
+    )
+  })?;
+  parse_tokens(&tokens, precedence)
+    .map(|(_, equation)| equation)
+    .map_err(describe_parse_error)
+}
+
+/// Render a parse failure in terms of the source span of the offending
+/// token, falling back to a generic message when the input was simply
+/// truncated.
+fn describe_parse_error(err: nom::Err<nom::error::Error<&[Token]>>) -> String {
+  let remaining = match err {
+    nom::Err::Error(e) | nom::Err::Failure(e) => e.input,
+    nom::Err::Incomplete(_) => return "incomplete equation".to_string(),
+  };
+  match remaining.first() {
+    Some(token) => format!(
+      "unexpected token at byte {}..{}",
+      token.span.start, token.span.end
+    ),
+    None => "unexpected end of equation".to_string(),
+  }
+}
+
+/// Parse a full [Equation] from a token stream.
+fn parse_tokens(input: &[Token], precedence: Precedence) -> IResult<&[Token], Equation> {
+  let mut parser = tuple((|i| parse_expression(i, precedence), rhs));
   let (rest, (expression, expected)) = parser(input)?;
   Ok((
     rest,
@@ -21,38 +52,117 @@ pub fn parse_equation(input: &str) -> IResult<&str, Equation> {
   ))
 }
 
-/// Parse an optionally signed number.
-fn parse_number(input: &str) -> IResult<&str, f32> {
-  let fraction_parse = recognize(tuple((digit1, char('.'), digit1)));
-  let num_parse = delimited(
-    space0,
-    tuple((opt(char('-')), alt((fraction_parse, digit1)))),
-    space0,
-  );
-  let mut parser = map_res(num_parse, |(neg, n): (Option<_>, &str)| {
-    n.parse::<f32>()
-      .map(|num| if neg.is_some() { -num } else { num })
-  });
-  parser(input)
+/// Match a single [TokenKind::LParen].
+fn lparen(input: &[Token]) -> IResult<&[Token], ()> {
+  match input.first() {
+    Some(Token {
+      kind: TokenKind::LParen,
+      ..
+    }) => Ok((&input[1..], ())),
+    _ => Err(token_error(input)),
+  }
+}
+
+/// Match a single [TokenKind::RParen].
+fn rparen(input: &[Token]) -> IResult<&[Token], ()> {
+  match input.first() {
+    Some(Token {
+      kind: TokenKind::RParen,
+      ..
+    }) => Ok((&input[1..], ())),
+    _ => Err(token_error(input)),
+  }
+}
+
+/// Match a single [TokenKind::Equals].
+fn equals(input: &[Token]) -> IResult<&[Token], ()> {
+  match input.first() {
+    Some(Token {
+      kind: TokenKind::Equals,
+      ..
+    }) => Ok((&input[1..], ())),
+    _ => Err(token_error(input)),
+  }
+}
+
+/// Match a single [TokenKind::Num], returning its value.
+fn num(input: &[Token]) -> IResult<&[Token], f32> {
+  match input.first() {
+    Some(Token {
+      kind: TokenKind::Num(n),
+      ..
+    }) => Ok((&input[1..], *n)),
+    _ => Err(token_error(input)),
+  }
+}
+
+/// Match a single [TokenKind::Op], returning its operator character.
+fn op(input: &[Token]) -> IResult<&[Token], char> {
+  match input.first() {
+    Some(Token {
+      kind: TokenKind::Op(c),
+      ..
+    }) => Ok((&input[1..], *c)),
+    _ => Err(token_error(input)),
+  }
+}
+
+/// Match a single [TokenKind::Ident], returning its name.
+fn ident(input: &[Token]) -> IResult<&[Token], &str> {
+  match input.first() {
+    Some(Token {
+      kind: TokenKind::Ident(name),
+      ..
+    }) => Ok((&input[1..], name.as_str())),
+    _ => Err(token_error(input)),
+  }
+}
+
+/// Build a nom error pointing at the front of `input`.
+fn token_error(input: &[Token]) -> nom::Err<nom::error::Error<&[Token]>> {
+  nom::Err::Error(nom::error::Error::new(input, nom::error::ErrorKind::Tag))
 }
 
 /// Parse the right hand side of the equation.
-fn rhs(input: &str) -> IResult<&str, f32> {
-  preceded(terminated(char('='), space0), parse_number)(input)
+fn rhs(input: &[Token]) -> IResult<&[Token], f32> {
+  preceded(equals, num)(input)
 }
 
 /// Parse an expression within parenthesis.
-fn parse_parens(input: &str) -> IResult<&str, Expr> {
-  delimited(
-    space0,
-    delimited(char('('), parse_expression, char(')')),
-    space0,
-  )(input)
+fn parse_parens(input: &[Token], precedence: Precedence) -> IResult<&[Token], Expr> {
+  delimited(lparen, |i| parse_expression(i, precedence), rparen)(input)
+}
+
+/// Parse a unary function call, e.g. `sin(0)`, into its [Expr] variant.
+fn parse_function(input: &[Token], precedence: Precedence) -> IResult<&[Token], Expr> {
+  let (input, name) = ident(input)?;
+  let (input, expr) = parse_parens(input, precedence)?;
+  let expr = Box::new(expr);
+  match name {
+    "sin" => Ok((input, Expr::Sin(expr))),
+    "cos" => Ok((input, Expr::Cos(expr))),
+    "exp" => Ok((input, Expr::Exp(expr))),
+    "ln" => Ok((input, Expr::Ln(expr))),
+    "sqrt" => Ok((input, Expr::Sqrt(expr))),
+    "abs" => Ok((input, Expr::Abs(expr))),
+    _ => Err(token_error(input)),
+  }
 }
 
-/// Parse a single term or an expression within parenthesis.
-fn parse_operation(input: &str) -> IResult<&str, Expr> {
-  alt((parse_parens, map(parse_number, Expr::Num)))(input)
+/// Parse a bare identifier as a variable reference.
+fn parse_variable(input: &[Token]) -> IResult<&[Token], Expr> {
+  map(ident, |name: &str| Expr::Var(name.to_string()))(input)
+}
+
+/// Parse a single term, a function call, a variable, or an expression within
+/// parenthesis.
+fn parse_operation(input: &[Token], precedence: Precedence) -> IResult<&[Token], Expr> {
+  alt((
+    |i| parse_parens(i, precedence),
+    |i| parse_function(i, precedence),
+    map(num, Expr::Num),
+    parse_variable,
+  ))(input)
 }
 
 /// Take two expressions [Expr] with an infix operator and return an [Expr]
@@ -69,64 +179,84 @@ fn parse_op((op, expr2): (char, Expr), expr1: Expr) -> Expr {
   }
 }
 
-/// Build a single recursive [Expr] from a list of individual [Expr] expressions.
-fn combine_exprs(expr: Expr, exprs: Vec<(char, Expr)>) -> Expr {
-  exprs.into_iter().fold(expr, |acc, val| parse_op(val, acc))
-}
-
-/// Parse expressions with factor/power of.
-fn parse_factor(input: &str) -> IResult<&str, Expr> {
-  let (input, num) = parse_operation(input)?;
-  let (input, exprs) = many0(tuple((char('^'), parse_factor)))(input)?;
-  Ok((input, combine_exprs(num, exprs)))
+/// Left and right binding power for an infix operator under a given
+/// [Precedence] profile. Under [Precedence::Standard] and
+/// [Precedence::AddBeforeMultiply], `^` is given a right binding power lower
+/// than its left so that it recurses into itself on the right and becomes
+/// right-associative (`2^3^2 == 2^(3^2)`); every other entry uses
+/// left < right so repeated applications fold left-associatively.
+fn binding_power(op: char, precedence: Precedence) -> (u8, u8) {
+  use Precedence::*;
+  match (precedence, op) {
+    (Standard, '+' | '-') => (1, 2),
+    (Standard, '*' | '/') => (3, 4),
+    (Standard, '^') => (9, 8),
+    (FlatLeftToRight, '+' | '-' | '*' | '/' | '^') => (1, 2),
+    (AddBeforeMultiply, '+' | '-') => (3, 4),
+    (AddBeforeMultiply, '*' | '/') => (1, 2),
+    (AddBeforeMultiply, '^') => (9, 8),
+    _ => unreachable!(),
+  }
 }
 
-/// Parse factor then division / multiplication.
-fn parse_term(input: &str) -> IResult<&str, Expr> {
-  let (input, num) = parse_factor(input)?;
-  let (input, exprs) =
-    many0(tuple((alt((char('/'), char('*'))), parse_factor)))(input)?;
-  Ok((input, combine_exprs(num, exprs)))
+/// Parse an expression, only consuming an infix operator while its left
+/// binding power exceeds `min_bp`. This is the Pratt-parser core: it replaces
+/// the old factor/term/expression cascade with a single precedence-driven
+/// loop, and every operator's precedence under each [Precedence] profile is
+/// set in one table, [binding_power].
+fn parse_expression_bp(
+  input: &[Token],
+  min_bp: u8,
+  precedence: Precedence,
+) -> IResult<&[Token], Expr> {
+  let (mut input, mut lhs) = parse_operation(input, precedence)?;
+  while let Ok((rest, operator)) = op(input) {
+    let (left_bp, right_bp) = binding_power(operator, precedence);
+    if left_bp <= min_bp {
+      break;
+    }
+    let (rest, rhs) = parse_expression_bp(rest, right_bp, precedence)?;
+    lhs = parse_op((operator, rhs), lhs);
+    input = rest;
+  }
+  Ok((input, lhs))
 }
 
-/// Parse factor then division / multiplication then addition subtraction expressions.
-fn parse_expression(input: &str) -> IResult<&str, Expr> {
-  let (input, num) = parse_term(input)?;
-  let (input, exprs) =
-    many0(tuple((alt((char('+'), char('-'))), parse_term)))(input)?;
-  Ok((input, combine_exprs(num, exprs)))
+/// Parse a full expression at the lowest precedence.
+fn parse_expression(input: &[Token], precedence: Precedence) -> IResult<&[Token], Expr> {
+  parse_expression_bp(input, 0, precedence)
 }
 
 #[cfg(test)]
 mod test {
   use super::*;
 
-  /// Test success by passing expected parsed value otherwise test failure.
-  macro_rules! test_parse_number {
-    ($input:literal, $expected:expr) => {
-      let (_, n) = parse_number($input).unwrap();
-      assert_eq!(n, $expected);
-    };
-    ($input:literal) => {
-      let result = parse_number($input);
-      assert!(result.is_err());
-    };
+  #[test]
+  fn test_exponent_right_associative() {
+    let equation = "2^3^2 = 512".parse::<Equation>().unwrap();
+    assert!(equation.is_correct());
   }
 
   #[test]
-  fn test_number() {
-    test_parse_number!("10.5", 10.5);
-    test_parse_number!("3.141592653589793", std::f32::consts::PI);
-    test_parse_number!(" 12345 --", 12345.);
-    test_parse_number!("12345 and", 12345.);
-    test_parse_number!(" 12345blah", 12345.);
-    test_parse_number!("-20.5-3", -20.5);
-    test_parse_number!("-10", -10.);
+  fn test_precedence_profiles() {
+    let flat = Equation::from_str_with(
+      "1 + 2 * 3 + 4 * 5 + 6 = 71",
+      Precedence::FlatLeftToRight,
+    )
+    .unwrap();
+    assert!(flat.is_correct());
+
+    let add_first = Equation::from_str_with(
+      "1 + 2 * 3 + 4 * 5 + 6 = 231",
+      Precedence::AddBeforeMultiply,
+    )
+    .unwrap();
+    assert!(add_first.is_correct());
   }
 
   #[test]
-  fn test_parse_fail() {
-    test_parse_number!("abc10.5");
-    test_parse_number!("+10");
+  fn test_invalid_equation_reports_byte_span() {
+    let err = "1 + & = 2".parse::<Equation>().unwrap_err();
+    assert!(format!("{err:?}").contains("4..5"));
   }
 }